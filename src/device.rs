@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use crate::native::{NativeWiimote, WIIMOTE_PLUS_PRODUCT_ID, WIIMOTE_PRODUCT_ID};
+use crate::result::{WiimoteDeviceError, WiimoteResult};
+
+/// Output report that asks a remote to reply with a status report (0x20).
+const STATUS_REQUEST_REPORT_ID: u8 = 0x15;
+/// Input report a remote sends in response to a status request, carrying buttons,
+/// extension/LED flags and the current battery level.
+const STATUS_REPORT_ID: u8 = 0x20;
+/// Bit in a status report's flags byte set when the remote considers its battery nearly empty.
+const LOW_BATTERY_FLAG: u8 = 0x01;
+/// Raw battery level in a status report corresponding to a full charge.
+const MAX_BATTERY_LEVEL: u8 = 0xc8;
+
+/// A connected Wii remote.
+pub struct WiimoteDevice {
+    native: NativeWiimote,
+    manually_disconnected: bool,
+    battery_level: Option<u8>,
+    low_battery: bool,
+}
+
+impl WiimoteDevice {
+    pub(crate) fn new(native: NativeWiimote) -> WiimoteResult<Self> {
+        if native.vendor_id() != crate::native::NINTENDO_VENDOR_ID {
+            return Err(WiimoteDeviceError::InvalidVendorID(native.vendor_id()).into());
+        }
+        if native.product_id() != WIIMOTE_PRODUCT_ID && native.product_id() != WIIMOTE_PLUS_PRODUCT_ID
+        {
+            return Err(WiimoteDeviceError::InvalidProductID(native.product_id()).into());
+        }
+
+        Ok(Self {
+            native,
+            manually_disconnected: false,
+            battery_level: None,
+            low_battery: false,
+        })
+    }
+
+    pub(crate) fn reconnect(&mut self, native: NativeWiimote) -> WiimoteResult<()> {
+        self.native = native;
+        self.manually_disconnected = false;
+        Ok(())
+    }
+
+    /// Identifier of this remote, stable across reconnects.
+    #[must_use]
+    pub fn identifier(&self) -> String {
+        self.native.identifier()
+    }
+
+    /// Whether this remote was deliberately disconnected by the application.
+    #[must_use]
+    pub fn manually_disconnected(&self) -> bool {
+        self.manually_disconnected
+    }
+
+    /// Marks this remote as deliberately disconnected, so [`crate::manager::WiimoteManager`]
+    /// stops tracking it instead of trying to reconnect it on the next scan.
+    pub fn disconnect(&mut self) {
+        self.manually_disconnected = true;
+    }
+
+    /// Sends the status report request and, if the remote replies within `timeout`,
+    /// decodes the battery level and low-battery flag it carries.
+    pub fn request_status(&mut self, timeout: Duration) -> WiimoteResult<()> {
+        self.native.write_report(&[STATUS_REQUEST_REPORT_ID, 0x00])?;
+
+        let report = self.native.read_report(timeout)?;
+        if report.first() == Some(&STATUS_REPORT_ID) {
+            self.decode_status_report(&report);
+        }
+
+        Ok(())
+    }
+
+    fn decode_status_report(&mut self, report: &[u8]) {
+        if let Some((low_battery, battery_level)) = parse_status_report(report) {
+            self.low_battery = low_battery;
+            self.battery_level = Some(battery_level);
+        }
+    }
+
+    /// Raw battery level (0-0xc8) from the last status report, if one has been received.
+    #[must_use]
+    pub fn battery_level(&self) -> Option<u8> {
+        self.battery_level
+    }
+
+    /// Battery charge as a percentage (0.0-100.0), derived from [`WiimoteDevice::battery_level`].
+    #[must_use]
+    pub fn battery_percent(&self) -> Option<f32> {
+        self.battery_level.map(battery_percent_from_level)
+    }
+
+    /// Whether the last status report signalled that the battery is nearly empty.
+    #[must_use]
+    pub fn low_battery(&self) -> bool {
+        self.low_battery
+    }
+}
+
+/// Status report byte layout: `[id, buttons_lo, buttons_hi, flags, _, _, battery]`.
+/// Returns `None` if `report` is too short to carry the flags/battery bytes.
+fn parse_status_report(report: &[u8]) -> Option<(bool, u8)> {
+    let (&flags, &battery_level) = (report.get(3)?, report.get(6)?);
+    Some((flags & LOW_BATTERY_FLAG != 0, battery_level))
+}
+
+fn battery_percent_from_level(level: u8) -> f32 {
+    (f32::from(level) / f32::from(MAX_BATTERY_LEVEL) * 100.0).min(100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flags_and_level_from_status_report() {
+        let report = [STATUS_REPORT_ID, 0x00, 0x00, LOW_BATTERY_FLAG, 0x00, 0x00, 0x32];
+        assert_eq!(parse_status_report(&report), Some((true, 0x32)));
+    }
+
+    #[test]
+    fn missing_bytes_parse_to_none() {
+        assert_eq!(parse_status_report(&[STATUS_REPORT_ID]), None);
+    }
+
+    #[test]
+    fn battery_percent_scales_and_caps_at_100() {
+        assert!((battery_percent_from_level(MAX_BATTERY_LEVEL) - 100.0).abs() < f32::EPSILON);
+        assert_eq!(battery_percent_from_level(0), 0.0);
+        // A level above MAX_BATTERY_LEVEL shouldn't report more than 100%.
+        assert!((battery_percent_from_level(u8::MAX) - 100.0).abs() < f32::EPSILON);
+    }
+}
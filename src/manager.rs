@@ -1,78 +1,271 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 use crate::device::WiimoteDevice;
 use crate::native::{wiimotes_scan, NativeWiimote};
+use crate::pairing;
+use crate::result::{WiimoteError, WiimoteResult};
+
+/// How long the scan thread sleeps between checks of its shutdown flag while
+/// waiting out the scan interval, so dropping a manager doesn't have to wait
+/// out a long backoff delay before its thread joins.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 type MutexWiimoteDevice = Arc<Mutex<WiimoteDevice>>;
 
+/// An event describing a change in the set of Wii remotes the manager knows about.
+#[derive(Debug, Clone)]
+pub enum WiimoteEvent {
+    /// A Wii remote the manager isn't tracking was connected: one never seen
+    /// before, or one seen again after being manually disconnected or forgotten.
+    Connected(MutexWiimoteDevice),
+    /// A Wii remote with the given identifier is no longer present.
+    ///
+    /// This fires both when a remote is manually disconnected and when it
+    /// simply drops off the air (e.g. powering off) and disappears from a scan.
+    Disconnected(String),
+    /// A previously seen Wii remote showed up in a scan again.
+    Reconnected(MutexWiimoteDevice),
+}
+
+/// Strategy used by [`WiimoteManager`] to decide how long to wait between scans.
+enum ScanInterval {
+    /// Always wait the same amount of time between scans.
+    Fixed(Duration),
+    /// Wait `current`, multiplying it by `factor` (capped at `max`) every time a
+    /// scan finds nothing new, and resetting back to `base` as soon as it does.
+    Backoff {
+        base: Duration,
+        max: Duration,
+        factor: f64,
+        current: Duration,
+    },
+}
+
+impl ScanInterval {
+    fn current(&self) -> Duration {
+        match self {
+            ScanInterval::Fixed(interval) => *interval,
+            ScanInterval::Backoff { current, .. } => *current,
+        }
+    }
+
+    /// Adjust the backoff delay based on whether the last scan found anything new.
+    fn on_scan_result(&mut self, found_activity: bool) {
+        if let ScanInterval::Backoff {
+            base,
+            max,
+            factor,
+            current,
+        } = self
+        {
+            *current = if found_activity {
+                *base
+            } else {
+                Duration::from_secs_f64((current.as_secs_f64() * *factor).min(max.as_secs_f64()))
+            };
+        }
+    }
+}
+
+/// Battery charge of a single Wii remote, as reported in its last status report.
+#[derive(Debug, Clone)]
+pub struct DeviceBatteryStatus {
+    /// Identifier of the device this status belongs to, as used by [`WiimoteManager::seen_devices`].
+    pub identifier: String,
+    /// Battery charge in percent (0.0-100.0).
+    pub percent: f32,
+    /// Whether the remote has signalled that its battery is running low.
+    pub low_battery: bool,
+}
+
 /// Manages connections to Wii remotes.
 /// Periodically checks for new connections of Wii remotes.
 pub struct WiimoteManager {
     seen_devices: HashMap<String, MutexWiimoteDevice>,
-    scan_interval: Duration,
-    new_devices_receiver: Option<calloop::channel::Channel<MutexWiimoteDevice>>,
+    /// Identifiers that were present in the previous scan, tracked separately
+    /// from `seen_devices` (which also holds devices that have since dropped
+    /// off) so reconnections can be told apart from a device's first sighting.
+    previously_present: HashSet<String>,
+    scan_interval: ScanInterval,
+    subscribers: Vec<calloop::channel::Sender<WiimoteEvent>>,
+    shutdown: Arc<AtomicBool>,
+    scan_thread: Option<JoinHandle<()>>,
 }
 
 impl WiimoteManager {
     pub fn new() -> Arc<Mutex<Self>> {
         Self::new_with_interval(Duration::from_millis(500))
     }
-    
+
     pub fn new_with_interval(scan_interval: Duration) -> Arc<Mutex<Self>> {
-        // Make sure only one manager exists at a time
-        static WIIMOTE_MANAGER_INITIALIZED: AtomicBool = AtomicBool::new(false);
-        
-        let prev_initialized = WIIMOTE_MANAGER_INITIALIZED.swap(true, Ordering::SeqCst);
-        if prev_initialized {
-            panic!("Several WiimoteManagers created in the same application!");
-        }
-        
-        let (new_devices_sender, new_devices_receiver) = calloop::channel::channel();
+        Self::new_internal(ScanInterval::Fixed(scan_interval))
+    }
+
+    /// Creates a manager that scans at `base` while remotes are actively
+    /// connecting or reconnecting, backing off by `factor` (e.g. `2.0` to
+    /// double) towards `max` each time a scan finds no new or reconnected
+    /// remote, and resetting back to `base` the moment one does. Disconnections
+    /// alone don't reset the delay.
+    pub fn new_with_backoff(base: Duration, max: Duration, factor: f64) -> Arc<Mutex<Self>> {
+        Self::new_internal(ScanInterval::Backoff {
+            base,
+            max,
+            factor,
+            current: base,
+        })
+    }
+
+    /// Fallible version of [`WiimoteManager::new`].
+    ///
+    /// Unlike `new`, which panics if the scan thread can't be spawned, this
+    /// surfaces that failure as a [`WiimoteError`]. Any number of managers may
+    /// coexist; each owns its own scan thread and its own set of seen devices.
+    pub fn try_new() -> WiimoteResult<Arc<Mutex<Self>>> {
+        Self::try_new_internal(ScanInterval::Fixed(Duration::from_millis(500)))
+    }
+
+    fn new_internal(scan_interval: ScanInterval) -> Arc<Mutex<Self>> {
+        Self::try_new_internal(scan_interval).expect("Failed to spawn Wii remote scan thread")
+    }
+
+    fn try_new_internal(scan_interval: ScanInterval) -> WiimoteResult<Arc<Mutex<Self>>> {
+        let shutdown = Arc::new(AtomicBool::new(false));
 
         let manager = Arc::new(Mutex::new(Self {
             seen_devices: HashMap::new(),
+            previously_present: HashSet::new(),
             scan_interval,
-            new_devices_receiver: Some(new_devices_receiver),
+            subscribers: Vec::new(),
+            shutdown: Arc::clone(&shutdown),
+            scan_thread: None,
         }));
 
         let weak_manager = Arc::downgrade(&manager);
-        std::thread::Builder::new()
+        let scan_thread = std::thread::Builder::new()
             .name("wii-remote-scan".to_string())
             .spawn(move || {
-                while let Some(manager) = weak_manager.upgrade() {
+                while !shutdown.load(Ordering::SeqCst) {
+                    let Some(manager) = weak_manager.upgrade() else {
+                        return;
+                    };
+
                     let interval = {
                         let mut manager = match manager.lock() {
                             Ok(m) => m,
                             Err(m) => m.into_inner(),
                         };
 
-                        let new_devices = manager.scan();
-                        let send_result = new_devices
-                            .into_iter()
-                            .try_for_each(|device| new_devices_sender.send(device));
-                        if send_result.is_err() {
-                            // Channel is disconnected, end scan thread
-                            return;
+                        let events = manager.scan();
+                        let found_activity = events.iter().any(|event| {
+                            matches!(
+                                event,
+                                WiimoteEvent::Connected(_) | WiimoteEvent::Reconnected(_)
+                            )
+                        });
+                        manager.scan_interval.on_scan_result(found_activity);
+
+                        for event in events {
+                            manager.publish(event);
                         }
 
-                        manager.scan_interval
+                        manager.scan_interval.current()
+                    };
+                    drop(manager);
+
+                    // Sleep in short steps so a shutdown request is noticed
+                    // promptly even when `interval` is long (e.g. after backoff).
+                    let mut remaining = interval;
+                    while remaining > Duration::ZERO && !shutdown.load(Ordering::SeqCst) {
+                        let step = remaining.min(SHUTDOWN_POLL_INTERVAL);
+                        std::thread::sleep(step);
+                        remaining -= step;
+                    }
+                }
+            })
+            .map_err(WiimoteError::ScanThreadSpawnFailed)?;
+
+        manager.lock().unwrap().scan_thread = Some(scan_thread);
+
+        Ok(manager)
+    }
+
+    /// Starts a pairing session that discovers and bonds un-paired Wii remotes
+    /// in inquiry range for `timeout`.
+    ///
+    /// Each newly bonded remote is added to [`WiimoteManager::seen_devices`]
+    /// and sent over the returned channel, so it is treated the same as an
+    /// already-paired remote by future scans.
+    pub fn start_pairing(
+        manager: &Arc<Mutex<Self>>,
+        timeout: Duration,
+    ) -> calloop::channel::Channel<MutexWiimoteDevice> {
+        let (bonded_sender, bonded_receiver) = calloop::channel::channel();
+        let weak_manager = Arc::downgrade(manager);
+
+        std::thread::Builder::new()
+            .name("wii-remote-pairing".to_string())
+            .spawn(move || {
+                for candidate in pairing::inquiry(timeout) {
+                    let Some(manager) = weak_manager.upgrade() else {
+                        return;
                     };
 
-                    std::thread::sleep(interval);
+                    match pairing::bond(candidate) {
+                        Ok(device) => {
+                            let identifier = device.identifier();
+                            let new_device = Arc::new(Mutex::new(device));
+
+                            let mut manager = match manager.lock() {
+                                Ok(m) => m,
+                                Err(m) => m.into_inner(),
+                            };
+                            manager
+                                .seen_devices
+                                .insert(identifier.clone(), Arc::clone(&new_device));
+                            // Newly bonded devices are already present as of this
+                            // scan, so this suppresses the spurious Reconnected
+                            // the next automatic scan would otherwise emit for them.
+                            manager.previously_present.insert(identifier);
+                            // The scan thread will therefore never publish a
+                            // Connected/Reconnected event for this device, so do
+                            // it here instead, otherwise subscribe() consumers
+                            // never learn about remotes paired this way.
+                            manager.publish(WiimoteEvent::Connected(Arc::clone(&new_device)));
+                            drop(manager);
+
+                            if bonded_sender.send(new_device).is_err() {
+                                // Channel is disconnected, no one is listening anymore
+                                return;
+                            }
+                        }
+                        Err(error) => eprintln!("Failed to pair wiimote: {error:?}"),
+                    }
                 }
             })
-            .expect("Failed to spawn Wii remote scan thread");
+            .expect("Failed to spawn Wii remote pairing thread");
 
-        manager
+        bonded_receiver
+    }
+
+    /// Forgets a previously bonded Wii remote so it no longer reconnects
+    /// automatically and must be paired again via [`WiimoteManager::start_pairing`].
+    pub fn forget(&mut self, identifier: &str) -> WiimoteResult<()> {
+        pairing::forget(identifier)?;
+        self.seen_devices.remove(identifier);
+        Ok(())
     }
 
     /// Set the interval at which the manager scans for Wii remotes.
+    ///
+    /// This switches the manager to a fixed interval, overriding any backoff
+    /// behavior configured via [`WiimoteManager::new_with_backoff`].
     pub fn set_scan_interval(&mut self, scan_interval: Duration) {
-        self.scan_interval = scan_interval;
+        self.scan_interval = ScanInterval::Fixed(scan_interval);
     }
 
     /// Collection of Wii remotes that are connected or have been connected previously.
@@ -81,38 +274,88 @@ impl WiimoteManager {
         self.seen_devices.values().map(Arc::clone).collect()
     }
 
-    /// Receiver of newly connected Wii remotes.
+    /// Battery charge of every seen Wii remote that has reported a status so far.
+    ///
+    /// Devices that haven't replied to a [`WiimoteDevice::request_status`] yet
+    /// are omitted rather than reported with a guessed value.
     #[must_use]
-    pub fn new_devices_receiver(&mut self) -> Option<calloop::channel::Channel<MutexWiimoteDevice>> {
-        mem::take(&mut self.new_devices_receiver)
+    pub fn battery_levels(&self) -> Vec<DeviceBatteryStatus> {
+        self.seen_devices
+            .iter()
+            .filter_map(|(identifier, device)| {
+                let device = device.lock().unwrap();
+                device
+                    .battery_percent()
+                    .map(|percent| DeviceBatteryStatus {
+                        identifier: identifier.clone(),
+                        percent,
+                        low_battery: device.low_battery(),
+                    })
+            })
+            .collect()
     }
 
-    /// Scan for connected Wii remotes.
-    fn scan(&mut self) -> Vec<MutexWiimoteDevice> {
-        // Cleanup manually disconnected devices to send them to the receiver again.
-        self.seen_devices.retain(|_, device| {
-            device
+    /// Registers a new subscriber for device and connection events.
+    ///
+    /// Every subscriber receives a clone of each event, so a GUI, a logger and
+    /// an input-mapper can all observe the same remotes independently.
+    #[must_use]
+    pub fn subscribe(&mut self) -> calloop::channel::Channel<WiimoteEvent> {
+        let (sender, receiver) = calloop::channel::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Sends `event` to every subscriber, dropping subscribers whose receiver
+    /// has gone away.
+    fn publish(&mut self, event: WiimoteEvent) {
+        self.subscribers
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Scan for connected Wii remotes, returning the connection, disconnection
+    /// and reconnection events observed since the previous scan.
+    fn scan(&mut self) -> Vec<WiimoteEvent> {
+        // What was actually present last time, as opposed to `seen_devices`,
+        // which also holds devices that have since disappeared.
+        let mut previously_present = mem::take(&mut self.previously_present);
+        let mut events = Vec::new();
+
+        // Cleanup manually disconnected devices so they can be seen again if they reconnect.
+        self.seen_devices.retain(|identifier, device| {
+            let keep = device
                 .try_lock()
-                .map_or(true, |d| !d.manually_disconnected())
+                .map_or(true, |d| !d.manually_disconnected());
+            if !keep && previously_present.remove(identifier) {
+                events.push(WiimoteEvent::Disconnected(identifier.clone()));
+            }
+            keep
         });
 
         let mut native_devices = Vec::new();
         wiimotes_scan(&mut native_devices);
 
-        let mut new_devices = Vec::new();
+        let mut new_ids = HashSet::new();
 
         for native_wiimote in native_devices {
             let identifier = native_wiimote.identifier();
+            new_ids.insert(identifier.clone());
+
             if let Some(existing_device) = self.seen_devices.get(&identifier) {
                 let result = existing_device.lock().unwrap().reconnect(native_wiimote);
-                if let Err(error) = result {
-                    eprintln!("Failed to reconnect wiimote: {error:?}");
+                match result {
+                    Ok(()) => {
+                        if !previously_present.contains(&identifier) {
+                            events.push(WiimoteEvent::Reconnected(Arc::clone(existing_device)));
+                        }
+                    }
+                    Err(error) => eprintln!("Failed to reconnect wiimote: {error:?}"),
                 }
             } else {
                 match WiimoteDevice::new(native_wiimote) {
                     Ok(device) => {
                         let new_device = Arc::new(Mutex::new(device));
-                        new_devices.push(Arc::clone(&new_device));
+                        events.push(WiimoteEvent::Connected(Arc::clone(&new_device)));
                         self.seen_devices.insert(identifier, new_device);
                     }
                     Err(error) => eprintln!("Failed to connect to wiimote: {error:?}"),
@@ -120,6 +363,68 @@ impl WiimoteManager {
             }
         }
 
-        new_devices
+        // Any identifier present last scan but no longer present in this one has
+        // dropped off the air (manual disconnects were reported above).
+        for identifier in previously_present.difference(&new_ids) {
+            events.push(WiimoteEvent::Disconnected(identifier.clone()));
+        }
+
+        self.previously_present = new_ids;
+
+        events
+    }
+}
+
+impl Drop for WiimoteManager {
+    /// Signals the scan thread to stop and joins it, instead of relying
+    /// solely on the thread noticing that its weak reference can no longer
+    /// be upgraded.
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(scan_thread) = self.scan_thread.take() {
+            // The scan thread briefly upgrades a weak reference to the manager
+            // while scanning; if that's the strong reference being dropped here,
+            // we're running on the scan thread itself, which is about to notice
+            // `shutdown` and return on its own. Joining it here would deadlock.
+            if scan_thread.thread().id() != std::thread::current().id() {
+                let _ = scan_thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max_then_resets_on_activity() {
+        let mut interval = ScanInterval::Backoff {
+            base: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            factor: 2.0,
+            current: Duration::from_millis(100),
+        };
+
+        interval.on_scan_result(false);
+        assert_eq!(interval.current(), Duration::from_millis(200));
+
+        interval.on_scan_result(false);
+        assert_eq!(interval.current(), Duration::from_millis(400));
+
+        // Capped at `max` rather than overshooting to 800ms.
+        interval.on_scan_result(false);
+        assert_eq!(interval.current(), Duration::from_millis(500));
+
+        interval.on_scan_result(true);
+        assert_eq!(interval.current(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn fixed_interval_ignores_scan_results() {
+        let mut interval = ScanInterval::Fixed(Duration::from_millis(500));
+        interval.on_scan_result(false);
+        interval.on_scan_result(true);
+        assert_eq!(interval.current(), Duration::from_millis(500));
     }
 }
@@ -0,0 +1,475 @@
+//! OS-level glue: finding already-bonded Wii remotes as HID devices, and the
+//! raw-HCI Bluetooth handshake used to bond new ones.
+
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use hidapi::{HidApi, HidDevice};
+
+use crate::result::{WiimoteDeviceError, WiimoteError, WiimoteResult};
+
+pub(crate) const NINTENDO_VENDOR_ID: u16 = 0x057e;
+pub(crate) const WIIMOTE_PRODUCT_ID: u16 = 0x0306;
+pub(crate) const WIIMOTE_PLUS_PRODUCT_ID: u16 = 0x0330;
+
+/// A Wii remote already bonded at the OS level, reachable as a HID device
+/// (the kernel's Bluetooth HID driver exposes a bonded remote the same way it
+/// would a USB HID device).
+pub struct NativeWiimote {
+    device: HidDevice,
+    identifier: String,
+    vendor_id: u16,
+    product_id: u16,
+}
+
+impl NativeWiimote {
+    pub(crate) fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    pub(crate) fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    pub(crate) fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub(crate) fn write_report(&self, data: &[u8]) -> WiimoteResult<()> {
+        self.device.write(data).map(|_| ()).map_err(|_| WiimoteError::Disconnected)
+    }
+
+    pub(crate) fn read_report(&self, timeout: Duration) -> WiimoteResult<Vec<u8>> {
+        let mut buf = [0u8; 22];
+        let timeout_ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        let read = self
+            .device
+            .read_timeout(&mut buf, timeout_ms)
+            .map_err(|_| WiimoteError::Disconnected)?;
+
+        if read == 0 {
+            return Err(WiimoteDeviceError::MissingData.into());
+        }
+
+        Ok(buf[..read].to_vec())
+    }
+}
+
+/// Scans the OS's HID layer for already-bonded Wii remotes, appending any found to `out`.
+pub(crate) fn wiimotes_scan(out: &mut Vec<NativeWiimote>) {
+    let Ok(api) = HidApi::new() else { return };
+
+    for info in api.device_list() {
+        if info.vendor_id() != NINTENDO_VENDOR_ID {
+            continue;
+        }
+        if info.product_id() != WIIMOTE_PRODUCT_ID && info.product_id() != WIIMOTE_PLUS_PRODUCT_ID {
+            continue;
+        }
+
+        let Ok(device) = info.open_device(&api) else {
+            continue;
+        };
+
+        let identifier = bonded_identifier(&info);
+        out.push(NativeWiimote {
+            device,
+            identifier,
+            vendor_id: info.vendor_id(),
+            product_id: info.product_id(),
+        });
+    }
+}
+
+/// Identifier for an already-bonded remote: its Bluetooth address if the kernel
+/// surfaces one as the HID serial number, falling back to its device path.
+fn bonded_identifier(info: &hidapi::DeviceInfo) -> String {
+    info.serial_number()
+        .map(str::to_string)
+        .unwrap_or_else(|| info.path().to_string_lossy().into_owned())
+}
+
+// --- Bluetooth sync/pairing -------------------------------------------------
+//
+// A Wii remote put into discoverable mode via its sync button (or holding 1+2)
+// doesn't use the normal PIN/SSP pairing flow: it expects the *host adapter's
+// own Bluetooth address, byte-reversed* as the link key. Bonding it is a raw
+// HCI inquiry followed by a connection where we answer the resulting
+// Link Key Request with that derived key ourselves instead of prompting for a PIN.
+
+const HCI_COMMAND_PKT: u8 = 0x01;
+const HCI_EVENT_PKT: u8 = 0x04;
+
+const OGF_LINK_CONTROL: u16 = 0x01;
+const OCF_INQUIRY: u16 = 0x0001;
+const OCF_CREATE_CONNECTION: u16 = 0x0005;
+const OCF_LINK_KEY_REQUEST_REPLY: u16 = 0x000b;
+const OGF_HOST_CTL: u16 = 0x03;
+const OCF_WRITE_STORED_LINK_KEY: u16 = 0x0011;
+const OCF_DELETE_STORED_LINK_KEY: u16 = 0x0012;
+
+const EVT_INQUIRY_COMPLETE: u8 = 0x01;
+const EVT_INQUIRY_RESULT: u8 = 0x02;
+const EVT_CONN_COMPLETE: u8 = 0x03;
+const EVT_LINK_KEY_REQUEST: u8 = 0x17;
+
+/// Device class of a Wii remote (peripheral / joystick), used to recognize it
+/// during inquiry before it's bonded and has a friendly name cached.
+const WIIMOTE_CLASS_OF_DEVICE: [u8; 3] = [0x04, 0x25, 0x00];
+
+/// A Bluetooth device address, in the little-endian wire order HCI sends it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BdAddr(pub [u8; 6]);
+
+impl BdAddr {
+    fn as_identifier(self) -> String {
+        let [a, b, c, d, e, f] = self.0;
+        format!("{f:02X}:{e:02X}:{d:02X}:{c:02X}:{b:02X}:{a:02X}")
+    }
+
+    /// The fixed link key a sync-button-discoverable remote expects: the host
+    /// adapter's own address, byte-reversed.
+    fn sync_button_link_key(host: BdAddr) -> [u8; 16] {
+        let mut key = [0u8; 16];
+        for (dst, src) in key.iter_mut().zip(host.0.iter().rev()) {
+            *dst = *src;
+        }
+        key
+    }
+}
+
+/// A Wii remote found in Bluetooth inquiry range that isn't bonded at the OS level yet.
+pub struct UnbondedWiimote {
+    address: BdAddr,
+}
+
+fn opcode(ogf: u16, ocf: u16) -> u16 {
+    (ogf << 10) | ocf
+}
+
+// Linux's `AF_BLUETOOTH` address family and `BTPROTO_HCI` protocol number
+// (from `<bluetooth/bluetooth.h>` / `<bluetooth/hci.h>`), which aren't exposed
+// by the `libc` crate.
+const AF_BLUETOOTH: libc::c_int = 31;
+const BTPROTO_HCI: libc::c_int = 1;
+
+/// The raw-HCI channel a bound socket sends/receives
+/// commands and events on (as opposed to a monitor or control channel).
+const HCI_CHANNEL_RAW: u16 = 0;
+
+/// Mirrors `struct sockaddr_hci` from `<bluetooth/hci.h>`.
+#[repr(C)]
+struct SockaddrHci {
+    hci_family: libc::sa_family_t,
+    hci_dev: u16,
+    hci_channel: u16,
+}
+
+/// Opens a raw HCI socket and binds it to the first adapter (`hci0`);
+/// selecting a specific controller on multi-adapter hosts is out of scope.
+fn open_hci_socket() -> WiimoteResult<OwnedFd> {
+    // SAFETY: libc::socket is called with valid, constant arguments; the
+    // returned fd is checked before being wrapped.
+    let fd = unsafe { libc::socket(AF_BLUETOOTH, libc::SOCK_RAW, BTPROTO_HCI) };
+    if fd < 0 {
+        return Err(WiimoteError::Disconnected);
+    }
+    // SAFETY: `fd` was just returned by `socket` and is owned by no one else.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // An HCI socket isn't associated with any controller until it's bound;
+    // without this, every command write fails with ENODEV.
+    let addr = SockaddrHci {
+        hci_family: AF_BLUETOOTH as libc::sa_family_t,
+        hci_dev: 0,
+        hci_channel: HCI_CHANNEL_RAW,
+    };
+    // SAFETY: `addr` is a valid `SockaddrHci` for the duration of the call.
+    let bound = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            std::ptr::addr_of!(addr).cast(),
+            mem::size_of::<SockaddrHci>() as libc::socklen_t,
+        )
+    };
+    if bound != 0 {
+        return Err(WiimoteError::Disconnected);
+    }
+
+    Ok(socket)
+}
+
+fn send_hci_command(socket: RawFd, opcode: u16, params: &[u8]) -> io::Result<()> {
+    let mut packet = Vec::with_capacity(4 + params.len());
+    packet.push(HCI_COMMAND_PKT);
+    packet.extend_from_slice(&opcode.to_le_bytes());
+    packet.push(params.len() as u8);
+    packet.extend_from_slice(params);
+
+    // SAFETY: `packet` is a valid, live buffer for the duration of the call.
+    let written = unsafe { libc::write(socket, packet.as_ptr().cast(), packet.len()) };
+    if written < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Reads the next HCI event, waiting up to `timeout`. Returns the event code and its parameters.
+fn read_hci_event(socket: RawFd, timeout: Duration) -> io::Result<(u8, Vec<u8>)> {
+    let mut pollfd = libc::pollfd {
+        fd: socket,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    // SAFETY: `pollfd` is a single, valid entry for the duration of the call.
+    let ready = unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as libc::c_int) };
+    if ready <= 0 {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, "no HCI event received in time"));
+    }
+
+    let mut buf = [0u8; 260];
+    // SAFETY: `buf` is valid for its full length for the duration of the call.
+    let read = unsafe { libc::read(socket, buf.as_mut_ptr().cast(), buf.len()) };
+    if read < 3 || buf[0] != HCI_EVENT_PKT {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed HCI packet"));
+    }
+
+    let event_code = buf[1];
+    let param_len = buf[2] as usize;
+    Ok((event_code, buf[3..3 + param_len.min(read as usize - 3)].to_vec()))
+}
+
+/// Reads HCI events until `deadline` looking for one matching `event_code`.
+fn wait_for_event(socket: RawFd, event_code: u8, deadline: Instant) -> WiimoteResult<Vec<u8>> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(WiimoteError::Disconnected);
+        }
+
+        match read_hci_event(socket, remaining) {
+            Ok((code, params)) if code == event_code => return Ok(params),
+            Ok(_) => continue,
+            Err(_) => return Err(WiimoteError::Disconnected),
+        }
+    }
+}
+
+/// Scans for un-bonded Wii remotes in inquiry range for `timeout`, appending any found to `out`.
+pub(crate) fn bluetooth_inquiry(timeout: Duration, out: &mut Vec<UnbondedWiimote>) {
+    let Ok(socket) = open_hci_socket() else { return };
+    let fd = socket.as_raw_fd();
+
+    // General Inquiry Access Code (GIAC). The inquiry length is `timeout` in
+    // whole seconds, which HCI counts in units of 1.28 s, clamped to 1..=0x30;
+    // events are only collected until `timeout` elapses.
+    let inquiry_length = timeout.as_secs().clamp(1, 0x30) as u8;
+    let params = [0x33, 0x8b, 0x9e, inquiry_length, 0x00];
+    if send_hci_command(fd, opcode(OGF_LINK_CONTROL, OCF_INQUIRY), &params).is_err() {
+        return;
+    }
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let Ok((event_code, params)) = read_hci_event(fd, deadline.saturating_duration_since(Instant::now())) else {
+            break;
+        };
+
+        match event_code {
+            EVT_INQUIRY_RESULT => {
+                let Some(&num_responses) = params.first() else { continue };
+                for i in 0..num_responses as usize {
+                    let Some(chunk) = params.get(1 + i * 6..7 + i * 6) else { continue };
+                    let class_offset = 1 + num_responses as usize * 9 + i * 3;
+                    let class_of_device = params.get(class_offset..class_offset + 3);
+
+                    if class_of_device == Some(&WIIMOTE_CLASS_OF_DEVICE[..]) {
+                        let mut address = [0u8; 6];
+                        address.copy_from_slice(chunk);
+                        out.push(UnbondedWiimote { address: BdAddr(address) });
+                    }
+                }
+            }
+            EVT_INQUIRY_COMPLETE => break,
+            _ => continue,
+        }
+    }
+}
+
+/// Performs the sync-button bonding handshake with `candidate`: connects to it
+/// and answers its link key request with the host-address-derived fixed key.
+pub(crate) fn pair_wiimote(candidate: UnbondedWiimote) -> WiimoteResult<NativeWiimote> {
+    let socket = open_hci_socket()?;
+    let fd = socket.as_raw_fd();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let address = candidate.address;
+
+    // Create Connection: bdaddr + packet_type + page_scan_repetition_mode + reserved
+    // + clock_offset + allow_role_switch.
+    let mut params = Vec::with_capacity(13);
+    params.extend_from_slice(&address.0);
+    params.extend_from_slice(&0xcc18u16.to_le_bytes());
+    params.push(0x02);
+    params.push(0x00);
+    params.extend_from_slice(&0x0000u16.to_le_bytes());
+    params.push(0x01);
+    send_hci_command(fd, opcode(OGF_LINK_CONTROL, OCF_CREATE_CONNECTION), &params)
+        .map_err(|_| WiimoteError::Disconnected)?;
+
+    // The controller emits Connection Complete as soon as the physical link is
+    // up, and only then starts authentication by asking us for a link key.
+    wait_for_event(fd, EVT_CONN_COMPLETE, deadline)?;
+    wait_for_event(fd, EVT_LINK_KEY_REQUEST, deadline)?;
+
+    let host = host_address(fd)?;
+    let link_key = BdAddr::sync_button_link_key(host);
+    let mut reply_params = Vec::with_capacity(22);
+    reply_params.extend_from_slice(&address.0);
+    reply_params.extend_from_slice(&link_key);
+    send_hci_command(fd, opcode(OGF_LINK_CONTROL, OCF_LINK_KEY_REQUEST_REPLY), &reply_params)
+        .map_err(|_| WiimoteError::Disconnected)?;
+
+    // Store the derived key so the controller itself remembers the bond and the
+    // remote reconnects automatically without repeating this handshake.
+    let mut stored_key_params = Vec::with_capacity(24);
+    stored_key_params.push(0x01); // num_keys
+    stored_key_params.extend_from_slice(&address.0);
+    stored_key_params.extend_from_slice(&link_key);
+    send_hci_command(fd, opcode(OGF_HOST_CTL, OCF_WRITE_STORED_LINK_KEY), &stored_key_params)
+        .map_err(|_| WiimoteError::Disconnected)?;
+
+    // Persisting the bond causes the kernel's Bluetooth HID driver to bind and
+    // expose the remote as a HID device; wait for it to show up.
+    find_bonded_device(address, Duration::from_secs(5))
+}
+
+/// Linux's `ioctl(2)` request number for `HCIGETDEVINFO` (`_IOR('H', 211, int)`).
+const HCIGETDEVINFO: libc::c_ulong = 0x800448d3;
+
+#[repr(C)]
+struct HciDevStats {
+    err_rx: u32,
+    err_tx: u32,
+    cmd_tx: u32,
+    evt_rx: u32,
+    acl_tx: u32,
+    acl_rx: u32,
+    sco_tx: u32,
+    sco_rx: u32,
+    byte_rx: u32,
+    byte_tx: u32,
+}
+
+#[repr(C)]
+struct HciDevInfo {
+    dev_id: u16,
+    name: [libc::c_char; 8],
+    bdaddr: [u8; 6],
+    flags: u32,
+    dev_type: u8,
+    features: [u8; 8],
+    pkt_type: u32,
+    link_policy: u32,
+    link_mode: u32,
+    acl_mtu: u16,
+    acl_pkts: u16,
+    sco_mtu: u16,
+    sco_pkts: u16,
+    stat: HciDevStats,
+}
+
+/// The adapter `socket` is bound to, read back via `getsockname` rather than
+/// assumed, so this targets whichever controller `open_hci_socket` chose.
+fn bound_dev_id(socket: RawFd) -> WiimoteResult<u16> {
+    let mut addr: SockaddrHci = unsafe { mem::zeroed() };
+    let mut addr_len = mem::size_of::<SockaddrHci>() as libc::socklen_t;
+
+    // SAFETY: `addr`/`addr_len` are a valid, uniquely-owned buffer and its
+    // size for the call's duration.
+    let result = unsafe { libc::getsockname(socket, std::ptr::addr_of_mut!(addr).cast(), &mut addr_len) };
+    if result != 0 {
+        return Err(WiimoteError::Disconnected);
+    }
+
+    Ok(addr.hci_dev)
+}
+
+/// The adapter's own Bluetooth address, needed to derive the sync-button link key.
+fn host_address(socket: RawFd) -> WiimoteResult<BdAddr> {
+    // SAFETY: `info` is zero-initialized; `dev_id` is set before the ioctl, which
+    // only reads/writes within the bounds of the `HciDevInfo` we pass it.
+    let mut info: HciDevInfo = unsafe { mem::zeroed() };
+    info.dev_id = bound_dev_id(socket)?;
+
+    // SAFETY: `info` is a valid, uniquely-owned `HciDevInfo` for the call's duration.
+    let result = unsafe { libc::ioctl(socket, HCIGETDEVINFO, &mut info) };
+    if result != 0 {
+        return Err(WiimoteError::Disconnected);
+    }
+
+    Ok(BdAddr(info.bdaddr))
+}
+
+/// Waits up to `timeout` for a bonded remote with the given address to appear
+/// as a HID device, opening it once it does.
+fn find_bonded_device(address: BdAddr, timeout: Duration) -> WiimoteResult<NativeWiimote> {
+    let deadline = Instant::now() + timeout;
+    let target = address.as_identifier();
+
+    while Instant::now() < deadline {
+        let mut found = Vec::new();
+        wiimotes_scan(&mut found);
+        // `bonded_identifier` echoes the kernel's HID serial number verbatim
+        // (commonly lowercase), while `target` comes from our own uppercase
+        // `BdAddr::as_identifier`, so compare case-insensitively.
+        if let Some(device) = found
+            .into_iter()
+            .find(|device| device.identifier.eq_ignore_ascii_case(&target))
+        {
+            return Ok(device);
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    Err(WiimoteError::Disconnected)
+}
+
+/// Persists the link key for a bonded remote so the OS reconnects it automatically.
+pub(crate) fn persist_bond(native: &NativeWiimote) -> WiimoteResult<()> {
+    let _ = native;
+    // The stored link key is written as part of the pairing handshake itself
+    // (`OCF_WRITE_STORED_LINK_KEY` in `pair_wiimote`); once `pair_wiimote` succeeds the bond
+    // already persists across reboots, so this is a no-op kept for API symmetry
+    // with `forget`.
+    Ok(())
+}
+
+/// Drops a previously persisted bond, so the remote will no longer
+/// automatically reconnect and must be paired again to be used.
+pub(crate) fn forget_bond(identifier: &str) -> WiimoteResult<()> {
+    let socket = open_hci_socket()?;
+    let fd = socket.as_raw_fd();
+
+    let address = parse_identifier(identifier).ok_or(WiimoteError::Disconnected)?;
+    let mut params = Vec::with_capacity(7);
+    params.extend_from_slice(&address.0);
+    params.push(0x00);
+
+    send_hci_command(fd, opcode(OGF_HOST_CTL, OCF_DELETE_STORED_LINK_KEY), &params)
+        .map_err(|_| WiimoteError::Disconnected)
+}
+
+fn parse_identifier(identifier: &str) -> Option<BdAddr> {
+    let mut bytes = [0u8; 6];
+    let parts: Vec<&str> = identifier.split(':').collect();
+    if parts.len() != 6 {
+        return None;
+    }
+    for (i, part) in parts.iter().enumerate() {
+        bytes[5 - i] = u8::from_str_radix(part, 16).ok()?;
+    }
+    Some(BdAddr(bytes))
+}
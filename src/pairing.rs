@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use crate::device::WiimoteDevice;
+use crate::native::{self, UnbondedWiimote};
+use crate::result::WiimoteResult;
+
+/// Performs a Bluetooth inquiry for the given `timeout`, returning every Wii
+/// remote found in range that isn't bonded at the OS level yet.
+pub fn inquiry(timeout: Duration) -> Vec<UnbondedWiimote> {
+    let mut unbonded = Vec::new();
+    native::bluetooth_inquiry(timeout, &mut unbonded);
+    unbonded
+}
+
+/// Performs the sync-button / 1+2-button HID pairing handshake with `candidate`
+/// using the fixed-for-sync-button link key, and persists the resulting bond
+/// so the remote reconnects automatically in future scans.
+pub fn bond(candidate: UnbondedWiimote) -> WiimoteResult<WiimoteDevice> {
+    let native_wiimote = native::pair_wiimote(candidate)?;
+    native::persist_bond(&native_wiimote)?;
+    WiimoteDevice::new(native_wiimote)
+}
+
+/// Drops a previously persisted bond, so the remote will no longer
+/// automatically reconnect and must be paired again to be used.
+pub fn forget(identifier: &str) -> WiimoteResult<()> {
+    native::forget_bond(identifier)
+}
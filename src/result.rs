@@ -4,6 +4,7 @@ use std::fmt::Display;
 pub enum WiimoteError {
     WiimoteDeviceError(WiimoteDeviceError),
     Disconnected,
+    ScanThreadSpawnFailed(std::io::Error),
 }
 
 impl Display for WiimoteError {
@@ -11,6 +12,9 @@ impl Display for WiimoteError {
         match self {
             WiimoteError::WiimoteDeviceError(wiimote_device_error) => wiimote_device_error.fmt(f),
             WiimoteError::Disconnected => write!(f, "Disconnected"),
+            WiimoteError::ScanThreadSpawnFailed(error) => {
+                write!(f, "Failed to spawn Wii remote scan thread: {error}")
+            }
         }
     }
 }